@@ -0,0 +1,169 @@
+//! Decoding of the binary Beast format used by dump1090's Beast output port.
+//!
+//! Beast frames are escaped with `0x1a`: a frame starts with `0x1a` followed
+//! by a type byte (`'1'` Mode-AC, `'2'` Mode-S short, `'3'` Mode-S long), then
+//! a 6-byte MLAT timestamp, a 1-byte signal level, and the payload itself.
+//! Any literal `0x1a` byte inside the timestamp/signal/payload is doubled on
+//! the wire and must be un-escaped back to a single byte.
+
+use tokio::io::{AsyncReadExt, BufReader};
+use tokio::net::TcpStream;
+
+const ESCAPE: u8 = 0x1a;
+
+/// A single decoded Beast frame: a receive timestamp, signal level, and the
+/// raw Mode-S/Mode-AC payload extracted from the framing.
+#[derive(Debug, Clone)]
+pub struct BeastFrame {
+    pub mlat_timestamp: u64,
+    pub signal_level: u8,
+    pub payload: Vec<u8>,
+}
+
+/// Read and decode a single message from a Beast binary stream.
+///
+/// Returns `None` on EOF. Malformed frames (unknown type byte, truncated
+/// body) are skipped internally and reading resumes at the next escape
+/// byte, matching the best-effort handling of the AVR hex reader in `main`.
+pub async fn read_frame(stream: &mut BufReader<TcpStream>) -> Option<BeastFrame> {
+    // The type byte of the next frame, when a lone (non-doubled) escape was
+    // already found while reading the body of a frame being discarded --
+    // that escape's following byte is itself the next frame's type byte, so
+    // it's carried over here instead of being scanned for again.
+    let mut pending_type_byte: Option<u8> = None;
+
+    loop {
+        let type_byte = if let Some(type_byte) = pending_type_byte.take() {
+            type_byte
+        } else {
+            // scan for the escape byte that starts a frame
+            let mut byte = [0u8; 1];
+            loop {
+                stream.read_exact(&mut byte).await.ok()?;
+                if byte[0] == ESCAPE {
+                    break;
+                }
+            }
+
+            let mut type_byte = [0u8; 1];
+            stream.read_exact(&mut type_byte).await.ok()?;
+            type_byte[0]
+        };
+
+        let payload_len = match type_byte {
+            b'1' => 2,  // Mode-AC
+            b'2' => 7,  // Mode-S short
+            b'3' => 14, // Mode-S long / ADS-B
+            _ => continue, // unknown type, resync on the next escape byte
+        };
+
+        // 6-byte MLAT timestamp + 1-byte signal level + payload, all escaped
+        let mut body = Vec::with_capacity(7 + payload_len);
+        let mut truncated = false;
+        while body.len() < 7 + payload_len {
+            let mut b = [0u8; 1];
+            if stream.read_exact(&mut b).await.is_err() {
+                return None;
+            }
+            if b[0] == ESCAPE {
+                let mut next = [0u8; 1];
+                if stream.read_exact(&mut next).await.is_err() {
+                    return None;
+                }
+                if next[0] != ESCAPE {
+                    // a lone escape mid-frame means the sender never sent
+                    // the bytes we expected; `next` is the type byte of the
+                    // following real frame, not a byte to discard, so carry
+                    // it over instead of scanning for another escape.
+                    truncated = true;
+                    pending_type_byte = Some(next[0]);
+                    break;
+                }
+                body.push(ESCAPE);
+            } else {
+                body.push(b[0]);
+            }
+        }
+        if truncated || body.len() != 7 + payload_len {
+            continue;
+        }
+
+        let mlat_timestamp = body[..6].iter().fold(0u64, |acc, &b| (acc << 8) | b as u64);
+        let signal_level = body[6];
+        let payload = body[7..].to_vec();
+
+        return Some(BeastFrame {
+            mlat_timestamp,
+            signal_level,
+            payload,
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::io::AsyncWriteExt;
+    use tokio::net::TcpListener;
+
+    /// Hand `bytes` to a freshly connected loopback `TcpStream` so
+    /// `read_frame` has a real `BufReader<TcpStream>` to read from.
+    async fn reader_for(bytes: &[u8]) -> BufReader<TcpStream> {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let client = TcpStream::connect(addr).await.unwrap();
+        let (mut server, _) = listener.accept().await.unwrap();
+        server.write_all(bytes).await.unwrap();
+        server.shutdown().await.unwrap();
+        BufReader::new(client)
+    }
+
+    #[tokio::test]
+    async fn un_escapes_a_doubled_escape_byte_inside_the_payload() {
+        // type '2' (Mode-S short): 6-byte timestamp, 1-byte signal, 7-byte
+        // payload; the first payload byte is a literal 0x1a, doubled on the
+        // wire.
+        let wire = [
+            0x1a, b'2', // frame start
+            1, 2, 3, 4, 5, 6, // mlat timestamp
+            9, // signal level
+            ESCAPE, ESCAPE, 0xaa, 0xbb, 0xcc, 0xdd, 0xee, 0xff, // payload (escaped)
+        ];
+
+        let mut stream = reader_for(&wire).await;
+        let frame = read_frame(&mut stream).await.unwrap();
+
+        assert_eq!(frame.mlat_timestamp, 0x01_0203_0405_06);
+        assert_eq!(frame.signal_level, 9);
+        assert_eq!(frame.payload, vec![ESCAPE, 0xaa, 0xbb, 0xcc, 0xdd, 0xee, 0xff]);
+    }
+
+    #[tokio::test]
+    async fn resyncs_on_a_lone_escape_using_the_next_byte_as_the_new_type_byte() {
+        // A Mode-S short frame ('2') that is abandoned partway through its
+        // body by a lone (non-doubled) escape. The byte right after that
+        // escape is 'b'1'' -- the type byte of the next, complete, Mode-AC
+        // frame -- and must be used as such rather than discarded.
+        let wire = [
+            0x1a, b'2', // abandoned frame start
+            1, 2, 3, // a few real body bytes, then a lone escape:
+            0x1a, b'1', // lone escape, followed by next frame's type byte
+            10, 11, 12, 13, 14, 15, // mlat timestamp
+            16, // signal level
+            17, 18, // payload (Mode-AC is 2 bytes)
+        ];
+
+        let mut stream = reader_for(&wire).await;
+        let frame = read_frame(&mut stream).await.unwrap();
+
+        assert_eq!(frame.mlat_timestamp, 0x0a_0b0c_0d0e_0f);
+        assert_eq!(frame.signal_level, 16);
+        assert_eq!(frame.payload, vec![17, 18]);
+    }
+
+    #[tokio::test]
+    async fn returns_none_on_eof() {
+        let mut stream = reader_for(&[]).await;
+        assert!(read_frame(&mut stream).await.is_none());
+    }
+}
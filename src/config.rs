@@ -0,0 +1,118 @@
+//! Configuration loaded from a `--config` JSON/TOML file, merged with CLI
+//! flags (which always take precedence over the file).
+
+use std::net::SocketAddr;
+use std::path::Path;
+
+use serde::Deserialize;
+
+use crate::filter::{BoundingBox, Filter};
+use crate::{CliArgs, InputFormat};
+
+/// Receiver position, network addresses, and filter settings that can be
+/// set from a config file. Every field is optional so a file only needs to
+/// hold the values it cares about; anything left unset falls back to the
+/// matching CLI flag, and from there to a built-in default where one exists
+/// (`lat`, `long` and both addresses have none and must be set somewhere).
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct Config {
+    pub lat: Option<f64>,
+    pub long: Option<f64>,
+    pub serve_addr: Option<SocketAddr>,
+    pub dump1090_addr: Option<SocketAddr>,
+    pub format: Option<InputFormat>,
+    pub prune_seconds: Option<u64>,
+    pub max_range_km: Option<f64>,
+    pub bbox: Option<BoundingBox>,
+    pub max_altitude: Option<f64>,
+    pub ignore_categories: Vec<u8>,
+}
+
+impl Config {
+    /// Load a config file, inferring JSON vs. TOML from its extension
+    /// (anything other than `.json` is parsed as TOML).
+    fn load(path: &Path) -> Result<Self, String> {
+        let contents = std::fs::read_to_string(path)
+            .map_err(|e| format!("failed to read config file {path:?}: {e}"))?;
+        match path.extension().and_then(|ext| ext.to_str()) {
+            Some("json") => {
+                serde_json::from_str(&contents).map_err(|e| format!("failed to parse {path:?} as JSON: {e}"))
+            }
+            _ => toml::from_str(&contents).map_err(|e| format!("failed to parse {path:?} as TOML: {e}")),
+        }
+    }
+}
+
+/// Aircraft older than this are pruned from the tracked state by default.
+const DEFAULT_PRUNE_SECONDS: u64 = 120;
+/// `Airplanes::action`'s plausible-position cutoff, in kilometers, by default.
+const DEFAULT_MAX_RANGE_KM: f64 = 500.0;
+
+/// The fully resolved settings spotter runs with: CLI flags override the
+/// config file, which overrides spotter's built-in defaults.
+#[derive(Debug, Clone)]
+pub struct Settings {
+    pub lat: f64,
+    pub long: f64,
+    pub serve_addr: SocketAddr,
+    pub dump1090_addr: SocketAddr,
+    pub format: InputFormat,
+    pub prune_seconds: u64,
+    pub max_range_km: f64,
+    pub bbox: Option<BoundingBox>,
+    pub max_altitude: Option<f64>,
+    pub ignore_categories: Vec<u8>,
+}
+
+impl Settings {
+    pub fn resolve(cli: CliArgs) -> Result<Self, String> {
+        let config = match cli.config.as_deref() {
+            Some(path) => Config::load(path)?,
+            None => Config::default(),
+        };
+
+        Ok(Self {
+            lat: cli
+                .lat
+                .or(config.lat)
+                .ok_or("lat is required on the CLI or in --config")?,
+            long: cli
+                .long
+                .or(config.long)
+                .ok_or("long is required on the CLI or in --config")?,
+            serve_addr: cli
+                .serve_addr
+                .or(config.serve_addr)
+                .ok_or("serve_addr is required on the CLI or in --config")?,
+            dump1090_addr: cli
+                .dump1090_addr
+                .or(config.dump1090_addr)
+                .ok_or("dump1090_addr is required on the CLI or in --config")?,
+            format: cli.format.or(config.format).unwrap_or(InputFormat::Avr),
+            prune_seconds: cli
+                .prune_seconds
+                .or(config.prune_seconds)
+                .unwrap_or(DEFAULT_PRUNE_SECONDS),
+            max_range_km: cli
+                .max_range_km
+                .or(config.max_range_km)
+                .unwrap_or(DEFAULT_MAX_RANGE_KM),
+            bbox: cli.bbox.or(config.bbox),
+            max_altitude: cli.max_altitude.or(config.max_altitude),
+            ignore_categories: if cli.ignore_categories.is_empty() {
+                config.ignore_categories
+            } else {
+                cli.ignore_categories
+            },
+        })
+    }
+
+    pub fn filter(&self) -> Filter {
+        Filter {
+            bbox: self.bbox,
+            max_altitude_ft: self.max_altitude,
+            ignore_categories: self.ignore_categories.clone(),
+        }
+    }
+}
@@ -0,0 +1,260 @@
+//! Supervises the TCP connection to dump1090: connects, decodes frames off
+//! it into the shared state, and reconnects with exponential backoff if the
+//! connection never comes up or drops mid-stream.
+
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use serde::Serialize;
+use tokio::io::{AsyncBufReadExt, BufReader};
+use tokio::net::TcpStream;
+use tokio::sync::Mutex;
+
+use adsb_deku::deku::prelude::*;
+use adsb_deku::Frame;
+use rsadsb_common::Airplanes;
+
+use crate::beast;
+use crate::config::Settings;
+use crate::cpr::CprPairGuard;
+use crate::events::EventBus;
+use crate::filter::{self, Categories};
+use crate::jitter::JitterBuffer;
+use crate::InputFormat;
+
+/// Delay before the first reconnect attempt.
+const INITIAL_BACKOFF: Duration = Duration::from_millis(500);
+/// Reconnect delay is doubled on every failure, capped at this.
+const MAX_BACKOFF: Duration = Duration::from_secs(30);
+
+/// Connection state exposed on `/health` and the home page.
+#[derive(Debug, Default)]
+pub struct ConnectionStatus {
+    connected: bool,
+    last_seen: Option<Instant>,
+    reconnect_count: u32,
+}
+
+/// A point-in-time, JSON-serializable snapshot of `ConnectionStatus`.
+#[derive(Debug, Clone, Serialize)]
+pub struct ConnectionStatusView {
+    pub connected: bool,
+    pub seconds_since_last_message: Option<f64>,
+    pub reconnect_count: u32,
+}
+
+impl ConnectionStatus {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn touch(&mut self) {
+        self.last_seen = Some(Instant::now());
+    }
+
+    pub fn snapshot(&self) -> ConnectionStatusView {
+        ConnectionStatusView {
+            connected: self.connected,
+            seconds_since_last_message: self.last_seen.map(|at| at.elapsed().as_secs_f64()),
+            reconnect_count: self.reconnect_count,
+        }
+    }
+}
+
+/// Connect to `args.dump1090_addr`, decode frames from it until the
+/// connection drops, and keep retrying with exponential backoff. Never
+/// returns.
+#[allow(clippy::too_many_arguments)]
+pub async fn run(
+    args: Settings,
+    adsb_airplanes: Arc<Mutex<Airplanes>>,
+    event_bus: Arc<Mutex<EventBus>>,
+    categories: Arc<Mutex<Categories>>,
+    jitter_buffer: Arc<Mutex<JitterBuffer>>,
+    status: Arc<Mutex<ConnectionStatus>>,
+    cpr_pair_guard: Arc<Mutex<CprPairGuard>>,
+) {
+    let mut backoff = INITIAL_BACKOFF;
+    let mut ever_connected = false;
+
+    loop {
+        let stream = match TcpStream::connect(args.dump1090_addr).await {
+            Ok(stream) => stream,
+            Err(e) => {
+                tracing::warn!("failed to connect to {}: {e}", args.dump1090_addr);
+                tokio::time::sleep(backoff).await;
+                backoff = (backoff * 2).min(MAX_BACKOFF);
+                continue;
+            }
+        };
+        tracing::info!("connected to {stream:?}");
+        backoff = INITIAL_BACKOFF;
+        {
+            let mut status = status.lock().await;
+            if ever_connected {
+                status.reconnect_count += 1;
+            }
+            status.connected = true;
+        }
+        ever_connected = true;
+
+        let mut stream = BufReader::new(stream);
+        match args.format {
+            InputFormat::Avr => {
+                read_avr(
+                    &mut stream,
+                    &adsb_airplanes,
+                    &event_bus,
+                    &categories,
+                    &jitter_buffer,
+                    &status,
+                    &cpr_pair_guard,
+                    &args,
+                )
+                .await;
+            }
+            InputFormat::Beast => {
+                read_beast(
+                    &mut stream,
+                    &adsb_airplanes,
+                    &event_bus,
+                    &categories,
+                    &jitter_buffer,
+                    &status,
+                    &cpr_pair_guard,
+                    &args,
+                )
+                .await;
+            }
+        }
+
+        status.lock().await.connected = false;
+        tracing::warn!("lost connection to dump1090, reconnecting");
+        tokio::time::sleep(backoff).await;
+        backoff = (backoff * 2).min(MAX_BACKOFF);
+    }
+}
+
+/// Read AVR text lines (lines starting with `*`, hex, CRLF) until EOF or a
+/// read error, decoding each into the shared state.
+#[allow(clippy::too_many_arguments)]
+async fn read_avr(
+    stream: &mut BufReader<TcpStream>,
+    adsb_airplanes: &Arc<Mutex<Airplanes>>,
+    event_bus: &Arc<Mutex<EventBus>>,
+    categories: &Arc<Mutex<Categories>>,
+    jitter_buffer: &Arc<Mutex<JitterBuffer>>,
+    status: &Arc<Mutex<ConnectionStatus>>,
+    cpr_pair_guard: &Arc<Mutex<CprPairGuard>>,
+    args: &Settings,
+) {
+    let mut input = String::new();
+    loop {
+        input.clear();
+        match stream.read_line(&mut input).await {
+            Ok(0) | Err(_) => return,
+            Ok(len) => {
+                // convert from string hex -> bytes
+                let hex = &mut input.to_string()[1..len - 2].to_string();
+                tracing::debug!("{}", hex.to_lowercase());
+                let bytes = if let Ok(bytes) = hex::decode(&hex) {
+                    bytes
+                } else {
+                    continue;
+                };
+
+                status.lock().await.touch();
+                decode_and_track(
+                    &bytes,
+                    adsb_airplanes,
+                    event_bus,
+                    categories,
+                    jitter_buffer,
+                    cpr_pair_guard,
+                    args,
+                )
+                .await;
+            }
+        }
+    }
+}
+
+/// Read Beast binary frames until EOF, decoding each into the shared state.
+#[allow(clippy::too_many_arguments)]
+async fn read_beast(
+    stream: &mut BufReader<TcpStream>,
+    adsb_airplanes: &Arc<Mutex<Airplanes>>,
+    event_bus: &Arc<Mutex<EventBus>>,
+    categories: &Arc<Mutex<Categories>>,
+    jitter_buffer: &Arc<Mutex<JitterBuffer>>,
+    status: &Arc<Mutex<ConnectionStatus>>,
+    cpr_pair_guard: &Arc<Mutex<CprPairGuard>>,
+    args: &Settings,
+) {
+    while let Some(frame) = beast::read_frame(stream).await {
+        tracing::debug!(
+            "beast: mlat={} signal={}",
+            frame.mlat_timestamp,
+            frame.signal_level
+        );
+        status.lock().await.touch();
+        decode_and_track(
+            &frame.payload,
+            adsb_airplanes,
+            event_bus,
+            categories,
+            jitter_buffer,
+            cpr_pair_guard,
+            args,
+        )
+        .await;
+    }
+}
+
+/// Decode raw Mode-S bytes, fold the result into the shared `Airplanes`
+/// state, record its emitter category and jitter-smoothed position, and
+/// broadcast any Appeared/Moved/Disappeared events that result.
+#[allow(clippy::too_many_arguments)]
+async fn decode_and_track(
+    bytes: &[u8],
+    adsb_airplanes: &Arc<Mutex<Airplanes>>,
+    event_bus: &Arc<Mutex<EventBus>>,
+    categories: &Arc<Mutex<Categories>>,
+    jitter_buffer: &Arc<Mutex<JitterBuffer>>,
+    cpr_pair_guard: &Arc<Mutex<CprPairGuard>>,
+    args: &Settings,
+) {
+    // check for all 0's
+    if bytes.iter().all(|&b| b == 0) {
+        return;
+    }
+
+    if let Ok((_, frame)) = Frame::from_bytes((bytes, 0)) {
+        let icao = filter::icao_of(&frame);
+        categories.lock().await.observe(&frame);
+
+        if let Some(icao) = icao {
+            if !cpr_pair_guard.lock().await.allow(&icao.to_string(), &frame) {
+                tracing::debug!("dropping stale CPR pair for {icao}");
+                return;
+            }
+        }
+
+        let mut a = adsb_airplanes.lock().await;
+        a.action(frame, (args.lat, args.long), args.max_range_km);
+
+        if let Some(icao) = icao {
+            let icao = icao.to_string();
+            if let Some((_, state)) = a.iter().find(|(i, _)| i.to_string() == icao) {
+                jitter_buffer.lock().await.observe(&icao, state);
+            }
+        }
+
+        let mut events = event_bus.lock().await;
+        events.diff_and_broadcast(&a);
+
+        // remove airplanes that timed-out after `prune_seconds`
+        a.prune(args.prune_seconds);
+        events.prune(&a);
+    }
+}
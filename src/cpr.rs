@@ -0,0 +1,116 @@
+//! Guards against pairing ADS-B even/odd CPR position frames that arrived
+//! too far apart in time to trust the resulting globally-unambiguous fix.
+//!
+//! `rsadsb_common` solves a CPR position from the most recent even and odd
+//! frame seen for an aircraft; if the aircraft moved between them the
+//! pairing is no longer valid, but neither `adsb_deku` nor `rsadsb_common`
+//! expose a way to bound how stale that pair may be. We track each
+//! aircraft's last-seen parity and timestamp ourselves and keep the frame
+//! that would complete a too-old pair from ever reaching `Airplanes::action`.
+
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+use adsb_deku::adsb::{CPRFormat, ME};
+use adsb_deku::{Frame, DF};
+
+/// Maximum age gap allowed between the even and odd frame of a CPR pair.
+const MAX_PAIR_GAP: Duration = Duration::from_secs(2);
+
+/// Tracks the most recently seen CPR parity and receive time per aircraft.
+#[derive(Debug, Default)]
+pub struct CprPairGuard {
+    last: HashMap<String, (CPRFormat, Instant)>,
+}
+
+impl CprPairGuard {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Whether `frame` should be handed to `Airplanes::action`. Only `false`
+    /// when `frame` carries a CPR position of the opposite parity to the
+    /// last one seen for `icao`, and that previous frame is older than
+    /// `MAX_PAIR_GAP` -- pairing them would solve a position from fixes
+    /// taken too far apart in time to trust.
+    pub fn allow(&mut self, icao: &str, frame: &Frame) -> bool {
+        self.allow_parity(icao, cpr_parity(frame))
+    }
+
+    /// The actual pairing check, separated from `allow` so it can be
+    /// exercised without a `Frame` to build.
+    fn allow_parity(&mut self, icao: &str, parity: Option<CPRFormat>) -> bool {
+        let Some(parity) = parity else {
+            return true;
+        };
+        let now = Instant::now();
+        let allow = match self.last.get(icao) {
+            Some((previous_parity, at)) if *previous_parity != parity => {
+                now.saturating_duration_since(*at) <= MAX_PAIR_GAP
+            }
+            _ => true,
+        };
+        self.last.insert(icao.to_string(), (parity, now));
+        allow
+    }
+}
+
+/// The CPR even/odd flag carried by `frame`'s airborne position message, if
+/// it has one.
+fn cpr_parity(frame: &Frame) -> Option<CPRFormat> {
+    let DF::ADSB(adsb) = &frame.df else {
+        return None;
+    };
+    match &adsb.me {
+        ME::AirbornePositionBaroAltitude(altitude) | ME::AirbornePositionGNSSAltitude(altitude) => {
+            Some(altitude.odd_flag)
+        }
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn first_ever_frame_for_an_icao_passes() {
+        let mut guard = CprPairGuard::new();
+        assert!(guard.allow_parity("abc123", Some(CPRFormat::Even)));
+    }
+
+    #[test]
+    fn repeated_same_parity_frames_always_pass() {
+        let mut guard = CprPairGuard::new();
+        assert!(guard.allow_parity("abc123", Some(CPRFormat::Even)));
+        assert!(guard.allow_parity("abc123", Some(CPRFormat::Even)));
+        assert!(guard.allow_parity("abc123", Some(CPRFormat::Even)));
+    }
+
+    #[test]
+    fn opposite_parity_within_the_gap_passes() {
+        let mut guard = CprPairGuard::new();
+        assert!(guard.allow_parity("abc123", Some(CPRFormat::Even)));
+        assert!(guard.allow_parity("abc123", Some(CPRFormat::Odd)));
+    }
+
+    #[test]
+    fn opposite_parity_beyond_the_gap_is_rejected() {
+        let mut guard = CprPairGuard::new();
+        assert!(guard.allow_parity("abc123", Some(CPRFormat::Even)));
+
+        // Backdate the stored even frame past MAX_PAIR_GAP instead of
+        // actually sleeping for it.
+        let entry = guard.last.get_mut("abc123").unwrap();
+        entry.1 -= MAX_PAIR_GAP + Duration::from_millis(1);
+
+        assert!(!guard.allow_parity("abc123", Some(CPRFormat::Odd)));
+    }
+
+    #[test]
+    fn frames_without_a_cpr_position_always_pass() {
+        let mut guard = CprPairGuard::new();
+        assert!(guard.allow_parity("abc123", None));
+        assert!(guard.allow_parity("abc123", None));
+    }
+}
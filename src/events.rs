@@ -0,0 +1,121 @@
+//! Live Appeared/Moved/Disappeared event stream, broadcast to subscribers
+//! of the `/events` route.
+//!
+//! Each time the decode loop folds a frame into `Airplanes`, it diffs the
+//! new state against the previous snapshot and sends any resulting events
+//! on a `tokio::sync::broadcast` channel. `/events` subscribes a fresh
+//! receiver per client and streams the events out as Server-Sent Events.
+
+use std::collections::{HashMap, HashSet};
+
+use rsadsb_common::Airplanes;
+use serde::Serialize;
+use tokio::sync::broadcast;
+
+use crate::geo::haversine_km;
+
+/// Minimum position change, in kilometers, before a tracked aircraft is
+/// reported as `Moved` rather than ignored as GPS/CPR noise.
+const MOVED_THRESHOLD_KM: f64 = 0.1;
+
+/// Number of events a lagging subscriber may fall behind before the oldest
+/// are dropped for it.
+const CHANNEL_CAPACITY: usize = 256;
+
+/// An update to the tracked airplane set, pushed to `/events` subscribers.
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "action", rename_all = "snake_case")]
+pub enum Event {
+    Appeared(AirplaneSnapshot),
+    Moved(AirplaneSnapshot),
+    Disappeared { icao: String },
+}
+
+/// The fields of an airplane's state that subscribers care about.
+#[derive(Debug, Clone, Serialize)]
+pub struct AirplaneSnapshot {
+    pub icao: String,
+    pub flight: Option<String>,
+    pub lat: Option<f64>,
+    pub lon: Option<f64>,
+    pub altitude_m: Option<f64>,
+}
+
+impl AirplaneSnapshot {
+    fn distance_km(&self, other: &AirplaneSnapshot) -> Option<f64> {
+        let (lat1, lon1) = (self.lat?, self.lon?);
+        let (lat2, lon2) = (other.lat?, other.lon?);
+        Some(haversine_km(lat1, lon1, lat2, lon2))
+    }
+}
+
+/// Broadcasts `Event`s to any number of `/events` subscribers and retains
+/// the previous snapshot needed to diff the next update against.
+pub struct EventBus {
+    sender: broadcast::Sender<Event>,
+    previous: HashMap<String, AirplaneSnapshot>,
+}
+
+impl EventBus {
+    pub fn new() -> Self {
+        let (sender, _) = broadcast::channel(CHANNEL_CAPACITY);
+        Self {
+            sender,
+            previous: HashMap::new(),
+        }
+    }
+
+    pub fn subscribe(&self) -> broadcast::Receiver<Event> {
+        self.sender.subscribe()
+    }
+
+    /// Diff `airplanes` against the last observed snapshot and broadcast
+    /// `Appeared`/`Moved` events for any aircraft added or meaningfully
+    /// moved since. Call `prune` separately, after `Airplanes::prune` has
+    /// timed aircraft out, to emit the matching `Disappeared` events.
+    pub fn diff_and_broadcast(&mut self, airplanes: &Airplanes) {
+        let mut current = HashMap::with_capacity(self.previous.len());
+        for (icao, state) in airplanes.iter() {
+            let icao = icao.to_string();
+            let snapshot = AirplaneSnapshot {
+                icao: icao.clone(),
+                flight: state.flight.clone(),
+                lat: state.coords.lat,
+                lon: state.coords.lon,
+                altitude_m: state.coords.altitude.map(f64::from),
+            };
+
+            match self.previous.get(&icao) {
+                None => {
+                    let _ = self.sender.send(Event::Appeared(snapshot.clone()));
+                }
+                Some(previous) => {
+                    let moved = snapshot
+                        .distance_km(previous)
+                        .is_some_and(|distance| distance > MOVED_THRESHOLD_KM);
+                    if moved {
+                        let _ = self.sender.send(Event::Moved(snapshot.clone()));
+                    }
+                }
+            }
+
+            current.insert(icao, snapshot);
+        }
+        self.previous = current;
+    }
+
+    /// Broadcast `Disappeared` for any previously tracked aircraft that is
+    /// no longer present in `airplanes`, e.g. after `Airplanes::prune`.
+    pub fn prune(&mut self, airplanes: &Airplanes) {
+        let seen: HashSet<String> = airplanes.iter().map(|(icao, _)| icao.to_string()).collect();
+        self.previous.retain(|icao, _| {
+            let still_present = seen.contains(icao);
+            if !still_present {
+                let _ = self.sender.send(Event::Disappeared {
+                    icao: icao.clone(),
+                });
+            }
+            still_present
+        });
+    }
+}
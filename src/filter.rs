@@ -0,0 +1,143 @@
+//! Geofence and category filtering applied before exposing aircraft on
+//! `/airplanes/filtered`: a bounding box, an altitude ceiling, and a list of
+//! ADS-B emitter categories to drop.
+
+use std::collections::HashMap;
+use std::str::FromStr;
+
+use adsb_deku::adsb::ME;
+use adsb_deku::{Frame, ICAO, DF};
+use rsadsb_common::AirplaneState;
+use serde::Deserialize;
+
+/// An `upper_lat,upper_lon,bottom_lat,bottom_lon` bounding box.
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(try_from = "String")]
+pub struct BoundingBox {
+    pub upper_lat: f64,
+    pub upper_lon: f64,
+    pub bottom_lat: f64,
+    pub bottom_lon: f64,
+}
+
+impl BoundingBox {
+    fn contains(&self, lat: f64, lon: f64) -> bool {
+        lat <= self.upper_lat
+            && lat >= self.bottom_lat
+            && lon <= self.upper_lon
+            && lon >= self.bottom_lon
+    }
+}
+
+impl TryFrom<String> for BoundingBox {
+    type Error = String;
+
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        s.parse()
+    }
+}
+
+impl FromStr for BoundingBox {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let parts = s
+            .split(',')
+            .map(|p| p.trim().parse::<f64>().map_err(|e| e.to_string()))
+            .collect::<Result<Vec<_>, _>>()?;
+        match parts[..] {
+            [upper_lat, upper_lon, bottom_lat, bottom_lon] => Ok(Self {
+                upper_lat,
+                upper_lon,
+                bottom_lat,
+                bottom_lon,
+            }),
+            _ => Err(format!(
+                "expected upper_lat,upper_lon,bottom_lat,bottom_lon, got {} value(s)",
+                parts.len()
+            )),
+        }
+    }
+}
+
+/// Active filters applied to the tracked airplane set.
+#[derive(Debug, Clone, Default)]
+pub struct Filter {
+    pub bbox: Option<BoundingBox>,
+    pub max_altitude_ft: Option<f64>,
+    pub ignore_categories: Vec<u8>,
+}
+
+impl Filter {
+    /// Whether `state` should be kept given the active filters. Aircraft
+    /// missing the data a filter needs (no position yet, category unknown)
+    /// are kept rather than dropped, since filtering should narrow down
+    /// known traffic, not hide aircraft we simply haven't decoded enough
+    /// of yet.
+    pub fn passes(&self, state: &AirplaneState, category: Option<u8>) -> bool {
+        if let Some(bbox) = self.bbox {
+            if let (Some(lat), Some(lon)) = (state.coords.lat, state.coords.lon) {
+                if !bbox.contains(lat, lon) {
+                    return false;
+                }
+            }
+        }
+        if let Some(max_altitude_ft) = self.max_altitude_ft {
+            if let Some(altitude) = state.coords.altitude {
+                if f64::from(altitude) > max_altitude_ft {
+                    return false;
+                }
+            }
+        }
+        if let Some(category) = category {
+            if self.ignore_categories.contains(&category) {
+                return false;
+            }
+        }
+        true
+    }
+}
+
+/// The ICAO address carried by an ADS-B frame, if it has one.
+pub fn icao_of(frame: &Frame) -> Option<ICAO> {
+    match &frame.df {
+        DF::ADSB(adsb) => Some(adsb.icao),
+        _ => None,
+    }
+}
+
+/// The Aircraft Identification type code for wake-vortex category "Set A".
+/// `ca` only means Light/Small/Large/High-Vortex-Large/Heavy (the meanings
+/// `--ignore-categories` documents, e.g. `3,4,5`) when it comes from this
+/// set; the same `ca` value from a Set B/C/D message (TC 1-3) means
+/// something else entirely, so those are left unrecorded rather than mixed
+/// into the same category space.
+const WAKE_VORTEX_SET_A_TC: u8 = 4;
+
+/// Tracks the most recently observed ADS-B emitter category per aircraft,
+/// taken from the `ca` field of Set A Aircraft Identification messages as
+/// they arrive.
+#[derive(Debug, Clone, Default)]
+pub struct Categories(HashMap<String, u8>);
+
+impl Categories {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Record the emitter category carried by `frame`, if it is a Set A
+    /// (TC 4) Aircraft Identification message.
+    pub fn observe(&mut self, frame: &Frame) {
+        if let DF::ADSB(adsb) = &frame.df {
+            if let ME::AircraftIdentification(identification) = &adsb.me {
+                if identification.tc == WAKE_VORTEX_SET_A_TC {
+                    self.0.insert(adsb.icao.to_string(), identification.ca);
+                }
+            }
+        }
+    }
+
+    pub fn get(&self, icao: &ICAO) -> Option<u8> {
+        self.0.get(&icao.to_string()).copied()
+    }
+}
@@ -0,0 +1,209 @@
+//! A per-aircraft jitter buffer that smooths decoded CPR positions.
+//!
+//! Each newly decoded position is shifted into a short ring of recent
+//! fixes; it only replaces the previously accepted, published position if
+//! it is plausible given the aircraft's last known ground speed and the
+//! time elapsed since that fix. This rejects the occasional single-frame
+//! CPR spike to a wildly wrong coordinate without needing to touch the
+//! CPR decoding itself.
+
+use std::collections::HashMap;
+use std::time::Instant;
+
+use rsadsb_common::AirplaneState;
+
+use crate::geo::haversine_km;
+
+/// How many recent fixes are kept per aircraft before a candidate is
+/// trusted enough to ever be published.
+const MIN_COOROBORATING_FIXES: usize = 3;
+const WINDOW: usize = 5;
+
+/// Generous speed ceiling (in knots) used to bound plausible movement when
+/// an aircraft's ground speed isn't yet known.
+const DEFAULT_MAX_SPEED_KNOTS: f64 = 700.0;
+
+/// The smallest plausibility radius applied regardless of elapsed time, so
+/// that two fixes a fraction of a second apart aren't both rejected as
+/// implausible.
+const MIN_PLAUSIBLE_KM: f64 = 0.05;
+
+#[derive(Debug, Clone, Copy)]
+struct Fix {
+    lat: f64,
+    lon: f64,
+    at: Instant,
+}
+
+#[derive(Debug)]
+struct Aircraft {
+    ring: Vec<Fix>,
+    accepted: Option<Fix>,
+    /// When this aircraft was first observed. Used as the reference fix's
+    /// age while bootstrapping (before anything has been `accepted` yet),
+    /// since the ring itself only ever holds freshly pushed fixes.
+    first_seen: Instant,
+}
+
+impl Aircraft {
+    fn new(now: Instant) -> Self {
+        Self {
+            ring: Vec::new(),
+            accepted: None,
+            first_seen: now,
+        }
+    }
+
+    fn push(&mut self, fix: Fix) {
+        self.ring.push(fix);
+        if self.ring.len() > WINDOW {
+            self.ring.remove(0);
+        }
+    }
+
+    /// The median of the ring's latitudes and longitudes, each sorted
+    /// independently; a cheap, outlier-resistant stand-in for a true 2D
+    /// median that's good enough for a handful of nearby points.
+    fn median(&self) -> (f64, f64) {
+        let mut lats: Vec<f64> = self.ring.iter().map(|f| f.lat).collect();
+        let mut lons: Vec<f64> = self.ring.iter().map(|f| f.lon).collect();
+        lats.sort_by(f64::total_cmp);
+        lons.sort_by(f64::total_cmp);
+        let mid = lats.len() / 2;
+        (lats[mid], lons[mid])
+    }
+}
+
+/// Smooths CPR-derived positions across every tracked aircraft, keyed by
+/// ICAO string.
+#[derive(Debug, Default)]
+pub struct JitterBuffer {
+    aircraft: HashMap<String, Aircraft>,
+}
+
+impl JitterBuffer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Feed `state`'s current raw position through the jitter buffer for
+    /// `icao`, updating the smoothed position that `smoothed` will return
+    /// for it. Does nothing if `state` doesn't have a position yet.
+    pub fn observe(&mut self, icao: &str, state: &AirplaneState) {
+        let (Some(lat), Some(lon)) = (state.coords.lat, state.coords.lon) else {
+            return;
+        };
+        let ground_speed_knots = state.coords.ground_speed.map(f64::from);
+        self.observe_position(icao, lat, lon, ground_speed_knots);
+    }
+
+    /// The actual plausibility check, separated from `observe` so it can be
+    /// exercised without an `AirplaneState` to build.
+    fn observe_position(
+        &mut self,
+        icao: &str,
+        lat: f64,
+        lon: f64,
+        ground_speed_knots: Option<f64>,
+    ) {
+        let now = Instant::now();
+        let candidate = Fix { lat, lon, at: now };
+        let aircraft = self
+            .aircraft
+            .entry(icao.to_string())
+            .or_insert_with(|| Aircraft::new(now));
+        aircraft.push(candidate);
+
+        if aircraft.ring.len() < MIN_COOROBORATING_FIXES {
+            return;
+        }
+
+        // The previously accepted fix is the natural reference once there
+        // is one. Before that, fall back to the median of the ring, whose
+        // age is how long this aircraft has been tracked at all -- not the
+        // age of the candidate that was just pushed onto it.
+        let (reference_lat, reference_lon, reference_at) = match aircraft.accepted {
+            Some(fix) => (fix.lat, fix.lon, fix.at),
+            None => {
+                let (median_lat, median_lon) = aircraft.median();
+                (median_lat, median_lon, aircraft.first_seen)
+            }
+        };
+        let elapsed = now.saturating_duration_since(reference_at).as_secs_f64();
+        let max_speed_knots = ground_speed_knots.unwrap_or(DEFAULT_MAX_SPEED_KNOTS);
+        let max_plausible_km = (max_speed_knots * 1.852 * elapsed / 3600.0).max(MIN_PLAUSIBLE_KM);
+
+        let distance_km = haversine_km(reference_lat, reference_lon, lat, lon);
+        if distance_km <= max_plausible_km {
+            aircraft.accepted = Some(candidate);
+        }
+        // otherwise: outlier, keep publishing the previously accepted fix
+    }
+
+    /// The smoothed position last accepted for `icao`, if any.
+    pub fn smoothed(&self, icao: &str) -> Option<(f64, f64)> {
+        self.aircraft
+            .get(icao)
+            .and_then(|a| a.accepted)
+            .map(|fix| (fix.lat, fix.lon))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::thread::sleep;
+    use std::time::Duration;
+
+    const ICAO: &str = "abc123";
+
+    #[test]
+    fn accepts_a_plausible_fix_once_the_aircraft_has_actually_been_tracked_for_a_while() {
+        let mut buffer = JitterBuffer::new();
+
+        // Two fixes at the same spot, then one ~555m away: comfortably
+        // implausible this early regardless of how the reference age is
+        // computed, so it's rejected and `accepted` stays `None` -- keeping
+        // every observation below on the bootstrap (no-`accepted`-yet) path
+        // this test means to exercise.
+        buffer.observe_position(ICAO, 50.0, 10.0, Some(700.0));
+        sleep(Duration::from_millis(150));
+        buffer.observe_position(ICAO, 50.0, 10.0, Some(700.0));
+        sleep(Duration::from_millis(150));
+        buffer.observe_position(ICAO, 50.005, 10.0, Some(700.0));
+        assert_eq!(buffer.smoothed(ICAO), None);
+        sleep(Duration::from_millis(150));
+
+        // ~111m from the ring's median: implausible for the 50m floor the
+        // old bug collapsed every bootstrap check to (it read the age of
+        // the fix just pushed, always ~0s, instead of the aircraft's actual
+        // tracking age), but well within a 700kt aircraft's plausible
+        // radius over the ~450ms that has actually elapsed since it was
+        // first seen.
+        buffer.observe_position(ICAO, 49.999, 10.0, Some(700.0));
+        assert_eq!(buffer.smoothed(ICAO), Some((49.999, 10.0)));
+    }
+
+    #[test]
+    fn rejects_a_single_frame_spike_far_outside_the_plausible_radius() {
+        let mut buffer = JitterBuffer::new();
+        for _ in 0..3 {
+            buffer.observe_position(ICAO, 50.0, 10.0, Some(50.0));
+        }
+        let accepted_before = buffer.smoothed(ICAO);
+        assert_eq!(accepted_before, Some((50.0, 10.0)));
+
+        // A multi-degree jump handed to a slow aircraft right away: wildly
+        // implausible regardless of how the bootstrap reference is computed.
+        buffer.observe_position(ICAO, 55.0, 10.0, Some(50.0));
+        assert_eq!(buffer.smoothed(ICAO), accepted_before);
+    }
+
+    #[test]
+    fn does_nothing_until_enough_corroborating_fixes_have_arrived() {
+        let mut buffer = JitterBuffer::new();
+        buffer.observe_position(ICAO, 50.0, 10.0, Some(200.0));
+        buffer.observe_position(ICAO, 50.0, 10.0, Some(200.0));
+        assert_eq!(buffer.smoothed(ICAO), None);
+    }
+}
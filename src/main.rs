@@ -1,35 +1,110 @@
+mod beast;
+mod config;
+mod connection;
+mod cpr;
+mod events;
+mod filter;
+mod geo;
+mod jitter;
+
 use axum::extract::Path;
-use clap::Parser;
+use clap::{Parser, ValueEnum};
+use futures::Stream;
+use std::path::PathBuf;
 use std::str::FromStr;
 use std::sync::Arc;
-use tokio::io::{AsyncBufReadExt, BufReader};
-use tokio::net::TcpStream;
 use tokio::sync::Mutex;
+use tokio_stream::wrappers::BroadcastStream;
+use tokio_stream::StreamExt;
 use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
 
-use adsb_deku::deku::prelude::*;
-use adsb_deku::{Frame, ICAO};
-use rsadsb_common::Airplanes;
+use adsb_deku::ICAO;
+use rsadsb_common::{AirplaneState, Airplanes};
 
+use axum::response::sse::{Event as SseEvent, KeepAlive, Sse};
 use axum::{response::IntoResponse, routing::get, Json, Router};
 use std::net::SocketAddr;
 
+use config::Settings;
+use connection::ConnectionStatus;
+use events::EventBus;
+use filter::{BoundingBox, Categories};
+use jitter::JitterBuffer;
+use serde::Serialize;
+
+/// Input framing understood on the dump1090 connection.
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq, serde::Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum InputFormat {
+    /// dump1090's AVR text format: lines starting with `*`, hex, CRLF.
+    Avr,
+    /// dump1090's binary Beast format.
+    Beast,
+}
+
 /// Rust ADS-B processor and web server providing information in json format
-#[derive(Parser, Debug, Clone, Copy)]
-pub struct Args {
-    lat: f64,
-    long: f64,
+///
+/// Receiver position and the two socket addresses can also be set from a
+/// `--config` file; any flag given here overrides the file.
+#[derive(Parser, Debug, Clone)]
+pub struct CliArgs {
+    lat: Option<f64>,
+    long: Option<f64>,
     #[arg(short, long)]
-    serve_addr: SocketAddr,
+    serve_addr: Option<SocketAddr>,
 
     #[arg(short, long)]
-    dump1090_addr: SocketAddr,
+    dump1090_addr: Option<SocketAddr>,
+
+    /// JSON or TOML file holding receiver position, addresses, prune
+    /// timeout, max-range, and filter settings
+    #[arg(long)]
+    config: Option<PathBuf>,
+
+    /// Framing of the data read from `dump1090_addr`
+    #[arg(long, value_enum)]
+    format: Option<InputFormat>,
+
+    /// Seconds of silence before a tracked aircraft is pruned
+    #[arg(long)]
+    prune_seconds: Option<u64>,
+
+    /// Reject position fixes further than this from the receiver, in km
+    #[arg(long)]
+    max_range_km: Option<f64>,
+
+    /// Geofence as `upper_lat,upper_lon,bottom_lat,bottom_lon`; aircraft
+    /// outside of it are dropped from `/airplanes/filtered`
+    #[arg(long)]
+    bbox: Option<BoundingBox>,
+
+    /// Altitude ceiling in feet; aircraft above it are dropped from
+    /// `/airplanes/filtered`
+    #[arg(long)]
+    max_altitude: Option<f64>,
+
+    /// ADS-B emitter categories to drop from `/airplanes/filtered`, e.g.
+    /// `3,4,5` to ignore large/heavy airliners. Only Set A (TC 4) Aircraft
+    /// Identification categories are matched against this list.
+    #[arg(long, value_delimiter = ',')]
+    ignore_categories: Vec<u8>,
 }
 
 #[tokio::main]
 async fn main() {
-    let args = Args::parse();
+    let args = match Settings::resolve(CliArgs::parse()) {
+        Ok(args) => args,
+        Err(e) => {
+            eprintln!("spotter: {e}");
+            std::process::exit(1);
+        }
+    };
     let adsb_airplanes = Arc::new(Mutex::new(Airplanes::new()));
+    let event_bus = Arc::new(Mutex::new(EventBus::new()));
+    let categories = Arc::new(Mutex::new(Categories::new()));
+    let jitter_buffer = Arc::new(Mutex::new(JitterBuffer::new()));
+    let connection_status = Arc::new(Mutex::new(ConnectionStatus::new()));
+    let cpr_pair_guard = Arc::new(Mutex::new(cpr::CprPairGuard::new()));
 
     // initialize tracing
     tracing_subscriber::registry()
@@ -45,81 +120,95 @@ async fn main() {
             "/",
             get({
                 let a = Arc::clone(&adsb_airplanes);
-                move |_: ()| home(a, args)
+                let args = args.clone();
+                let connection_status = Arc::clone(&connection_status);
+                move |_: ()| home(a, args, connection_status)
+            }),
+        )
+        .route(
+            "/health",
+            get({
+                let connection_status = Arc::clone(&connection_status);
+                move |_: ()| health(connection_status)
             }),
         )
         .route(
             "/airplanes",
             get({
                 let a = Arc::clone(&adsb_airplanes);
-                move |_: ()| airplanes_all(a)
+                let jitter_buffer = Arc::clone(&jitter_buffer);
+                move |_: ()| airplanes_all(a, jitter_buffer)
             }),
         )
         .route(
             "/airplane/closest",
             get({
                 let a = Arc::clone(&adsb_airplanes);
-                move |_: ()| closest_airplane(a)
+                let jitter_buffer = Arc::clone(&jitter_buffer);
+                move |_: ()| closest_airplane(a, jitter_buffer)
             }),
         )
         .route(
             "/airplane/furthest",
             get({
                 let a = Arc::clone(&adsb_airplanes);
-                move |_: ()| furthest_airplane(a)
+                let jitter_buffer = Arc::clone(&jitter_buffer);
+                move |_: ()| furthest_airplane(a, jitter_buffer)
             }),
         )
         .route(
             "/airplane/:icao",
             get({
                 let a = Arc::clone(&adsb_airplanes);
-                move |icao: Path<String>| airplane_icao(icao, a)
+                let jitter_buffer = Arc::clone(&jitter_buffer);
+                move |icao: Path<String>| airplane_icao(icao, a, jitter_buffer)
+            }),
+        )
+        .route(
+            "/airplanes/filtered",
+            get({
+                let a = Arc::clone(&adsb_airplanes);
+                let categories = Arc::clone(&categories);
+                let jitter_buffer = Arc::clone(&jitter_buffer);
+                let args = args.clone();
+                move |_: ()| airplanes_filtered(a, categories, jitter_buffer, args)
+            }),
+        )
+        .route(
+            "/events",
+            get({
+                let events = Arc::clone(&event_bus);
+                move |_: ()| events_stream(events)
             }),
         );
 
     tracing::info!("listening on {}", args.serve_addr);
     tokio::spawn(axum::Server::bind(&args.serve_addr).serve(app.into_make_service()));
 
-    let stream = TcpStream::connect(args.dump1090_addr).await.unwrap();
-    tracing::info!("connected to {stream:?}");
-    let mut stream = BufReader::new(stream);
-    let mut input = String::new();
-    loop {
-        input.clear();
-        if let Ok(len) = stream.read_line(&mut input).await {
-            if len == 0 {
-                continue;
-            }
-            // convert from string hex -> bytes
-            let hex = &mut input.to_string()[1..len - 2].to_string();
-            tracing::debug!("{}", hex.to_lowercase());
-            let bytes = if let Ok(bytes) = hex::decode(&hex) {
-                bytes
-            } else {
-                continue;
-            };
-
-            // check for all 0's
-            if bytes.iter().all(|&b| b == 0) {
-                continue;
-            }
-
-            // decode
-            if let Ok((_, frame)) = Frame::from_bytes((&bytes, 0)) {
-                let mut a = adsb_airplanes.lock().await;
-                a.action(frame, (args.lat, args.long), 500.0);
-
-                // remove airplanes that timed-out after 2 minutes
-                a.prune(120);
-            }
-        }
-    }
+    connection::run(
+        args,
+        adsb_airplanes,
+        event_bus,
+        categories,
+        jitter_buffer,
+        connection_status,
+        cpr_pair_guard,
+    )
+    .await;
 }
 
 // reply back with all airplanes
-async fn home(adsb_airplanes: Arc<Mutex<Airplanes>>, args: Args) -> impl IntoResponse {
+async fn home(
+    adsb_airplanes: Arc<Mutex<Airplanes>>,
+    args: Settings,
+    connection_status: Arc<Mutex<ConnectionStatus>>,
+) -> impl IntoResponse {
     tracing::info!("home");
     let a = adsb_airplanes.lock().await;
+    let status = connection_status.lock().await.snapshot();
+    let seconds_since_last_message = status
+        .seconds_since_last_message
+        .map_or_else(|| "never".to_string(), |secs| format!("{secs:.1}"));
     let body = format!(
         r#"Spotter - Rust ADS-B processor and web server providing information in json format
 
@@ -127,29 +216,87 @@ async fn home(adsb_airplanes: Arc<Mutex<Airplanes>>, args: Args) -> impl IntoRes
 Lat: {}
 Long: {}
 Airplanes tracked: {}
+Connected to dump1090: {}
+Seconds since last message: {}
+Reconnect count: {}
 
 ==[Protocol]=====
 /airplanes
 /airplanes/closest
 /airplanes/furthest
 /airplanes/:icao
+/airplanes/filtered
+/events
+/health
 "#,
         args.lat,
         args.long,
-        a.len()
+        a.len(),
+        status.connected,
+        seconds_since_last_message,
+        status.reconnect_count,
     );
 
     body
 }
 
+/// Reply with the dump1090 connection's health as JSON: whether it's
+/// currently connected, how long ago a message was last seen, and how many
+/// times spotter has had to reconnect.
+async fn health(connection_status: Arc<Mutex<ConnectionStatus>>) -> impl IntoResponse {
+    tracing::info!("health");
+    Json(connection_status.lock().await.snapshot())
+}
+
+/// Wraps a JSON-serializable airplane payload with its jitter-smoothed
+/// position, alongside the raw (possibly jumpy) fields underneath.
+#[derive(Debug, Serialize)]
+struct WithSmoothedPosition<T> {
+    #[serde(flatten)]
+    raw: T,
+    smoothed_lat: Option<f64>,
+    smoothed_lon: Option<f64>,
+}
+
+fn with_smoothed_position<T>(
+    raw: T,
+    icao: &str,
+    jitter_buffer: &JitterBuffer,
+) -> WithSmoothedPosition<T> {
+    let (smoothed_lat, smoothed_lon) = jitter_buffer
+        .smoothed(icao)
+        .map_or((None, None), |(lat, lon)| (Some(lat), Some(lon)));
+    WithSmoothedPosition {
+        raw,
+        smoothed_lat,
+        smoothed_lon,
+    }
+}
+
 // reply back with all airplanes
-async fn airplanes_all(adsb_airplanes: Arc<Mutex<Airplanes>>) -> impl IntoResponse {
+async fn airplanes_all(
+    adsb_airplanes: Arc<Mutex<Airplanes>>,
+    jitter_buffer: Arc<Mutex<JitterBuffer>>,
+) -> impl IntoResponse {
     tracing::info!("airplanes");
     let a = adsb_airplanes.lock().await;
-    Json(a.clone())
+    let jitter_buffer = jitter_buffer.lock().await;
+
+    let all: std::collections::BTreeMap<String, WithSmoothedPosition<AirplaneState>> = a
+        .iter()
+        .map(|(icao, state)| {
+            let icao = icao.to_string();
+            let view = with_smoothed_position(state.clone(), &icao, &jitter_buffer);
+            (icao, view)
+        })
+        .collect();
+    Json(all)
 }
 
-async fn closest_airplane(adsb_airplanes: Arc<Mutex<Airplanes>>) -> impl IntoResponse {
+async fn closest_airplane(
+    adsb_airplanes: Arc<Mutex<Airplanes>>,
+    jitter_buffer: Arc<Mutex<JitterBuffer>>,
+) -> impl IntoResponse {
     tracing::info!("closest");
     let a = adsb_airplanes.lock().await;
     let mut minimum = None;
@@ -165,10 +312,18 @@ async fn closest_airplane(adsb_airplanes: Arc<Mutex<Airplanes>>) -> impl IntoRes
             }
         }
     }
+    let jitter_buffer = jitter_buffer.lock().await;
+    let minimum = minimum.map(|(icao, state)| {
+        let view = with_smoothed_position(state, &icao, &jitter_buffer);
+        (icao, view)
+    });
     Json(minimum)
 }
 
-async fn furthest_airplane(adsb_airplanes: Arc<Mutex<Airplanes>>) -> impl IntoResponse {
+async fn furthest_airplane(
+    adsb_airplanes: Arc<Mutex<Airplanes>>,
+    jitter_buffer: Arc<Mutex<JitterBuffer>>,
+) -> impl IntoResponse {
     tracing::info!("furthest");
     let a = adsb_airplanes.lock().await;
     let mut minimum = None;
@@ -184,16 +339,65 @@ async fn furthest_airplane(adsb_airplanes: Arc<Mutex<Airplanes>>) -> impl IntoRe
             }
         }
     }
+    let jitter_buffer = jitter_buffer.lock().await;
+    let minimum = minimum.map(|(icao, state)| {
+        let view = with_smoothed_position(state, &icao, &jitter_buffer);
+        (icao, view)
+    });
     Json(minimum)
 }
 
 async fn airplane_icao(
     Path(icao): Path<String>,
     adsb_airplanes: Arc<Mutex<Airplanes>>,
+    jitter_buffer: Arc<Mutex<JitterBuffer>>,
 ) -> impl IntoResponse {
     tracing::info!(icao);
     let a = adsb_airplanes.lock().await;
 
     let details = a.aircraft_details(ICAO::from_str(&icao).unwrap());
-    Json(details)
+    let jitter_buffer = jitter_buffer.lock().await;
+    Json(with_smoothed_position(details, &icao, &jitter_buffer))
+}
+
+/// Reply with only the aircraft passing the active geofence/altitude/category
+/// filters, keeping the full, unfiltered state available on `/airplanes`.
+async fn airplanes_filtered(
+    adsb_airplanes: Arc<Mutex<Airplanes>>,
+    categories: Arc<Mutex<Categories>>,
+    jitter_buffer: Arc<Mutex<JitterBuffer>>,
+    args: Settings,
+) -> impl IntoResponse {
+    tracing::info!("airplanes/filtered");
+    let filter = args.filter();
+    let a = adsb_airplanes.lock().await;
+    let categories = categories.lock().await;
+    let jitter_buffer = jitter_buffer.lock().await;
+
+    let filtered: std::collections::BTreeMap<String, WithSmoothedPosition<AirplaneState>> = a
+        .iter()
+        .filter(|(icao, state)| filter.passes(state, categories.get(icao)))
+        .map(|(icao, state)| {
+            let icao = icao.to_string();
+            let view = with_smoothed_position(state.clone(), &icao, &jitter_buffer);
+            (icao, view)
+        })
+        .collect();
+
+    Json(filtered)
+}
+
+/// Stream Appeared/Moved/Disappeared events to a subscriber as
+/// Server-Sent Events, one JSON-encoded `events::Event` per message.
+async fn events_stream(
+    event_bus: Arc<Mutex<EventBus>>,
+) -> Sse<impl Stream<Item = Result<SseEvent, std::convert::Infallible>>> {
+    tracing::info!("events");
+    let receiver = event_bus.lock().await.subscribe();
+    let stream = BroadcastStream::new(receiver).filter_map(|event| {
+        let event = event.ok()?;
+        let json = serde_json::to_string(&event).ok()?;
+        Some(Ok(SseEvent::default().data(json)))
+    });
+    Sse::new(stream).keep_alive(KeepAlive::default())
 }